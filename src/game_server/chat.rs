@@ -62,6 +62,34 @@ pub struct MessagePayload {
     character_type: u32,
 }
 
+impl MessagePayload {
+    pub fn system(message: String) -> Self {
+        MessagePayload {
+            sender_guid: 0,
+            unknown1: 0,
+            unknown2: 0,
+            unknown3: 0,
+            unknown4: 0,
+            sender_first_name: "".to_string(),
+            sender_last_name: "".to_string(),
+            unknown5: 0,
+            unknown6: 0,
+            unknown7: 0,
+            target_first_name: "".to_string(),
+            target_last_name: "".to_string(),
+            message,
+            pos: Pos {
+                x: 0.0,
+                y: 0.0,
+                z: 0.0,
+                w: 0.0,
+            },
+            unknown8: 0,
+            character_type: 0,
+        }
+    }
+}
+
 pub enum SendMessage {
     World(MessagePayload),
     Whisper(MessagePayload),
@@ -76,6 +104,24 @@ pub enum SendMessage {
     MembersOnly(MessagePayload),
 }
 
+impl SendMessage {
+    fn payload_mut(&mut self) -> &mut MessagePayload {
+        match self {
+            SendMessage::World(payload) => payload,
+            SendMessage::Whisper(payload) => payload,
+            SendMessage::System(payload) => payload,
+            SendMessage::ReceivedItems(payload) => payload,
+            SendMessage::Group(payload) => payload,
+            SendMessage::Yell(payload) => payload,
+            SendMessage::Trade(payload) => payload,
+            SendMessage::LookingForGroup(payload) => payload,
+            SendMessage::Area(payload, _) => payload,
+            SendMessage::Guild(payload) => payload,
+            SendMessage::MembersOnly(payload) => payload,
+        }
+    }
+}
+
 impl SerializePacket for SendMessage {
     fn serialize(&self, buffer: &mut Vec<u8>) -> Result<(), SerializePacketError> {
         match self {
@@ -167,62 +213,36 @@ impl GamePacket for SendMessage {
 pub fn process_chat_packet(
     cursor: &mut Cursor<&[u8]>,
     sender: u32,
+    max_message_length: usize,
 ) -> Result<Vec<Broadcast>, ProcessPacketError> {
     let raw_op_code = cursor.read_u16::<LittleEndian>()?;
     match ChatOpCode::try_from(raw_op_code) {
         Ok(op_code) => match op_code {
             ChatOpCode::SendMessage => {
-                let message = SendMessage::deserialize(cursor)?;
+                let mut message = SendMessage::deserialize(cursor)?;
+                let payload = message.payload_mut();
+
+                if payload.message.trim().is_empty() {
+                    println!("Player {} sent an empty chat message", sender);
+                    return Ok(Vec::new());
+                }
+
+                // Truncate at the nearest character boundary so multibyte characters stay intact
+                if payload.message.len() > max_message_length {
+                    let mut length = max_message_length;
+                    while !payload.message.is_char_boundary(length) {
+                        length -= 1;
+                    }
+                    payload.message.truncate(length);
+                }
+
+                payload.sender_guid = player_guid(sender);
+
                 Ok(vec![Broadcast::Single(
                     sender,
                     vec![GamePacket::serialize(&TunneledPacket {
                         unknown1: true,
-                        inner: match message {
-                            SendMessage::World(mut payload) => {
-                                payload.sender_guid = player_guid(sender);
-                                SendMessage::World(payload)
-                            }
-                            SendMessage::Whisper(mut payload) => {
-                                payload.sender_guid = player_guid(sender);
-                                SendMessage::Whisper(payload)
-                            }
-                            SendMessage::System(mut payload) => {
-                                payload.sender_guid = player_guid(sender);
-                                SendMessage::System(payload)
-                            }
-                            SendMessage::ReceivedItems(mut payload) => {
-                                payload.sender_guid = player_guid(sender);
-                                SendMessage::ReceivedItems(payload)
-                            }
-                            SendMessage::Group(mut payload) => {
-                                payload.sender_guid = player_guid(sender);
-                                SendMessage::Group(payload)
-                            }
-                            SendMessage::Yell(mut payload) => {
-                                payload.sender_guid = player_guid(sender);
-                                SendMessage::Yell(payload)
-                            }
-                            SendMessage::Trade(mut payload) => {
-                                payload.sender_guid = player_guid(sender);
-                                SendMessage::Trade(payload)
-                            }
-                            SendMessage::LookingForGroup(mut payload) => {
-                                payload.sender_guid = player_guid(sender);
-                                SendMessage::LookingForGroup(payload)
-                            }
-                            SendMessage::Area(mut payload, unknown) => {
-                                payload.sender_guid = player_guid(sender);
-                                SendMessage::Area(payload, unknown)
-                            }
-                            SendMessage::Guild(mut payload) => {
-                                payload.sender_guid = player_guid(sender);
-                                SendMessage::Guild(payload)
-                            }
-                            SendMessage::MembersOnly(mut payload) => {
-                                payload.sender_guid = player_guid(sender);
-                                SendMessage::MembersOnly(payload)
-                            }
-                        },
+                        inner: message,
                     })?],
                 )])
             }
@@ -1,3 +1,4 @@
+use std::collections::BTreeSet;
 use std::io::Write;
 
 use byteorder::{LittleEndian, WriteBytesExt};
@@ -105,7 +106,7 @@ impl GamePacket for Power {
     const HEADER: ClientUpdateOpCode = ClientUpdateOpCode::Power;
 }
 
-#[derive(Copy, Clone, Debug)]
+#[derive(Copy, Clone, Debug, Eq, Ord, PartialEq, PartialOrd)]
 pub enum StatId {
     MaxHealth = 1,
     Speed = 2,
@@ -162,7 +163,22 @@ pub struct Stat {
 
 #[derive(SerializePacket)]
 pub struct Stats {
-    pub stats: Vec<Stat>,
+    stats: Vec<Stat>,
+}
+
+impl Stats {
+    // The client expects each stat at most once, so a repeated stat is a bug in the caller. Only
+    // check in debug builds so that a bad stat list can't take down a running server.
+    pub fn new(stats: Vec<Stat>) -> Self {
+        if cfg!(debug_assertions) {
+            let mut stat_ids = BTreeSet::new();
+            for stat in stats.iter() {
+                debug_assert!(stat_ids.insert(stat.id), "Stat {:?} sent twice", stat.id);
+            }
+        }
+
+        Stats { stats }
+    }
 }
 
 impl GamePacket for Stats {
@@ -179,3 +195,36 @@ impl GamePacket for PreloadCharactersDone {
     type Header = ClientUpdateOpCode;
     const HEADER: ClientUpdateOpCode = ClientUpdateOpCode::PreloadCharactersDone;
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn make_test_stat(id: StatId) -> Stat {
+        Stat {
+            id,
+            multiplier: 1,
+            value1: 0.0,
+            value2: 1.0,
+        }
+    }
+
+    #[test]
+    fn test_unique_stats() {
+        let stats = Stats::new(vec![
+            make_test_stat(StatId::Speed),
+            make_test_stat(StatId::PowerRegen),
+        ]);
+        assert_eq!(stats.stats.len(), 2);
+    }
+
+    #[test]
+    #[should_panic(expected = "Stat PowerRegen sent twice")]
+    fn test_duplicate_stat() {
+        Stats::new(vec![
+            make_test_stat(StatId::PowerRegen),
+            make_test_stat(StatId::Speed),
+            make_test_stat(StatId::PowerRegen),
+        ]);
+    }
+}
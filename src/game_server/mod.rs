@@ -44,7 +44,7 @@ use crate::game_server::tunnel::{TunneledPacket, TunneledWorldPacket};
 use crate::game_server::unique_guid::player_guid;
 use crate::game_server::update_position::UpdatePlayerPosition;
 use crate::game_server::zone::{
-    load_zones, teleport_within_zone, Character, Zone, ZoneTeleportRequest, ZoneTemplate,
+    load_zones, teleport_to_safety, Character, Zone, ZoneTeleportRequest, ZoneTemplate,
 };
 use crate::teleport_to_zone;
 
@@ -82,6 +82,11 @@ pub enum Broadcast {
 pub enum ProcessPacketError {
     CorruptedPacket,
     SerializeError(SerializePacketError),
+    FromPlayer {
+        sender: u32,
+        op_code: Option<OpCode>,
+        error: Box<ProcessPacketError>,
+    },
 }
 
 impl From<Error> for ProcessPacketError {
@@ -106,16 +111,22 @@ pub struct GameServer {
     lock_enforcer_source: LockEnforcerSource,
     mounts: BTreeMap<u32, MountConfig>,
     zone_templates: BTreeMap<u8, ZoneTemplate>,
+    max_chat_message_length: usize,
 }
 
 impl GameServer {
-    pub fn new(config_dir: &Path) -> Result<Self, Error> {
+    pub fn new(
+        config_dir: &Path,
+        max_npcs_per_zone: u16,
+        max_chat_message_length: usize,
+    ) -> Result<Self, Error> {
         let characters = GuidTable::new();
-        let (templates, zones) = load_zones(config_dir, characters.write())?;
+        let (templates, zones) = load_zones(config_dir, max_npcs_per_zone, characters.write())?;
         Ok(GameServer {
             lock_enforcer_source: LockEnforcerSource::from(characters, zones),
             mounts: load_mounts(config_dir)?,
             zone_templates: templates,
+            max_chat_message_length,
         })
     }
 
@@ -207,6 +218,27 @@ impl GameServer {
         &self,
         sender: u32,
         data: Vec<u8>,
+    ) -> Result<Vec<Broadcast>, ProcessPacketError> {
+        let op_code = data
+            .get(0..2)
+            .and_then(|bytes| OpCode::try_from(u16::from_le_bytes([bytes[0], bytes[1]])).ok());
+
+        self.process_unattributed_packet(sender, data)
+            .map_err(|err| match err {
+                // Keep the innermost op code for tunneled packets
+                ProcessPacketError::FromPlayer { .. } => err,
+                _ => ProcessPacketError::FromPlayer {
+                    sender,
+                    op_code,
+                    error: Box::new(err),
+                },
+            })
+    }
+
+    fn process_unattributed_packet(
+        &self,
+        sender: u32,
+        data: Vec<u8>,
     ) -> Result<Vec<Broadcast>, ProcessPacketError> {
         let mut broadcasts = Vec::new();
         let mut cursor = Cursor::new(&data[..]);
@@ -297,40 +329,32 @@ impl GameServer {
                                         if let Some(zone) = zones_read.get(&instance_guid) {
                                             let stats = TunneledPacket {
                                                 unknown1: true,
-                                                inner: Stats {
-                                                    stats: vec![
-                                                        Stat {
-                                                            id: StatId::Speed,
-                                                            multiplier: 1,
-                                                            value1: 0.0,
-                                                            value2: zone.speed,
-                                                        },
-                                                        Stat {
-                                                            id: StatId::PowerRegen,
-                                                            multiplier: 1,
-                                                            value1: 0.0,
-                                                            value2: 1.0,
-                                                        },
-                                                        Stat {
-                                                            id: StatId::PowerRegen,
-                                                            multiplier: 1,
-                                                            value1: 0.0,
-                                                            value2: 1.0,
-                                                        },
-                                                        Stat {
-                                                            id: StatId::GravityMultiplier,
-                                                            multiplier: 1,
-                                                            value1: 0.0,
-                                                            value2: zone.gravity_multiplier,
-                                                        },
-                                                        Stat {
-                                                            id: StatId::JumpHeightMultiplier,
-                                                            multiplier: 1,
-                                                            value1: 0.0,
-                                                            value2: zone.jump_height_multiplier,
-                                                        },
-                                                    ],
-                                                },
+                                                inner: Stats::new(vec![
+                                                    Stat {
+                                                        id: StatId::Speed,
+                                                        multiplier: 1,
+                                                        value1: 0.0,
+                                                        value2: zone.speed,
+                                                    },
+                                                    Stat {
+                                                        id: StatId::PowerRegen,
+                                                        multiplier: 1,
+                                                        value1: 0.0,
+                                                        value2: 1.0,
+                                                    },
+                                                    Stat {
+                                                        id: StatId::GravityMultiplier,
+                                                        multiplier: 1,
+                                                        value1: 0.0,
+                                                        value2: zone.gravity_multiplier,
+                                                    },
+                                                    Stat {
+                                                        id: StatId::JumpHeightMultiplier,
+                                                        multiplier: 1,
+                                                        value1: 0.0,
+                                                        value2: zone.jump_height_multiplier,
+                                                    },
+                                                ]),
                                             };
 
                                             Ok((GamePacket::serialize(&stats)?, Zone::character_guids(instance_guid, characters_table_read_handle)))
@@ -438,7 +462,7 @@ impl GameServer {
                     let pos_update: UpdatePlayerPosition =
                         DeserializePacket::deserialize(&mut cursor)?;
                     // TODO: broadcast pos update to all players
-                    broadcasts.append(&mut Zone::move_character(pos_update, self)?);
+                    broadcasts.append(&mut Zone::move_character(sender, pos_update, self)?);
                 }
                 OpCode::ZoneTeleportRequest => {
                     let teleport_request: ZoneTeleportRequest =
@@ -493,18 +517,16 @@ impl GameServer {
                 OpCode::TeleportToSafety => {
                     let mut packets = self.lock_enforcer().read_characters(|_| CharacterLockRequest {
                         read_guids: Vec::new(),
-                        write_guids: Vec::new(),
-                        character_consumer: |characters_table_read_handle, _, _, zones_lock_enforcer| {
-                            if let Some((instance_guid, _)) = characters_table_read_handle.index(player_guid(sender)) {
+                        write_guids: vec![player_guid(sender)],
+                        character_consumer: |_, _, mut characters_write, zones_lock_enforcer| {
+                            if let Some(character_write_handle) = characters_write.get_mut(&player_guid(sender)) {
+                                let instance_guid = character_write_handle.instance_guid;
                                 zones_lock_enforcer.read_zones(|_| ZoneLockRequest {
                                     read_guids: vec![instance_guid],
                                     write_guids: Vec::new(),
                                     zone_consumer: |_, zones_read, _| {
                                         if let Some(zone) = zones_read.get(&instance_guid) {
-                                            let spawn_pos = zone.default_spawn_pos;
-                                            let spawn_rot = zone.default_spawn_rot;
-
-                                            teleport_within_zone(sender, spawn_pos, spawn_rot)
+                                            teleport_to_safety(sender, character_write_handle, zone)
                                         } else {
                                             println!("Player {} outside zone tried to teleport to safety", sender);
                                             Err(ProcessPacketError::CorruptedPacket)
@@ -558,7 +580,11 @@ impl GameServer {
                     ));
                 }
                 OpCode::Chat => {
-                    broadcasts.append(&mut process_chat_packet(&mut cursor, sender)?);
+                    broadcasts.append(&mut process_chat_packet(
+                        &mut cursor,
+                        sender,
+                        self.max_chat_message_length,
+                    )?);
                 }
                 _ => println!("Unimplemented: {:?}, {:x?}", op_code, data),
             },
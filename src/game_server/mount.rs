@@ -151,28 +151,26 @@ pub fn reply_dismount(
                     })?,
                     GamePacket::serialize(&TunneledPacket {
                         unknown1: true,
-                        inner: Stats {
-                            stats: vec![
-                                Stat {
-                                    id: StatId::Speed,
-                                    multiplier: 1,
-                                    value1: 0.0,
-                                    value2: zone.speed,
-                                },
-                                Stat {
-                                    id: StatId::JumpHeightMultiplier,
-                                    multiplier: 1,
-                                    value1: 0.0,
-                                    value2: zone.jump_height_multiplier,
-                                },
-                                Stat {
-                                    id: StatId::GravityMultiplier,
-                                    multiplier: 1,
-                                    value1: 0.0,
-                                    value2: zone.gravity_multiplier,
-                                },
-                            ],
-                        },
+                        inner: Stats::new(vec![
+                            Stat {
+                                id: StatId::Speed,
+                                multiplier: 1,
+                                value1: 0.0,
+                                value2: zone.speed,
+                            },
+                            Stat {
+                                id: StatId::JumpHeightMultiplier,
+                                multiplier: 1,
+                                value1: 0.0,
+                                value2: zone.jump_height_multiplier,
+                            },
+                            Stat {
+                                id: StatId::GravityMultiplier,
+                                multiplier: 1,
+                                value1: 0.0,
+                                value2: zone.gravity_multiplier,
+                            },
+                        ]),
                     })?,
                 ],
             )])
@@ -270,8 +268,7 @@ fn process_mount_spawn(
 
                                 packets.push(GamePacket::serialize(&TunneledPacket {
                                     unknown1: true,
-                                    inner: Stats {
-                                        stats: vec![
+                                    inner: Stats::new(vec![
                                             Stat {
                                                 id: StatId::Speed,
                                                 multiplier: 1,
@@ -292,8 +289,7 @@ fn process_mount_spawn(
                                                 value2: zone_read_handle.gravity_multiplier
                                                     * mount.gravity_multiplier,
                                             },
-                                        ],
-                                    },
+                                        ]),
                                 })?);
 
                                 if let Some(mount_id) = character_write_handle.mount_id {
@@ -920,6 +920,8 @@ impl PlayerData {
             interact_radius: 0.0,
             auto_interact_radius: 0.0,
             instance_guid,
+            last_safety_teleport: None,
+            last_safety_message: None,
         }
     }
 }
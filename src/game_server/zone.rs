@@ -2,14 +2,17 @@ use std::collections::BTreeMap;
 use std::fs::File;
 use std::io::Error;
 use std::path::Path;
+use std::time::{Duration, Instant};
 
 use parking_lot::RwLockReadGuard;
+use rand::Rng;
 use serde::Deserialize;
 
 use packet_serialize::{DeserializePacket, SerializePacket, SerializePacketError};
 
 use strum::{EnumIter, IntoEnumIterator};
 
+use crate::game_server::chat::{MessagePayload, SendMessage};
 use crate::game_server::client_update_packet::Position;
 use crate::game_server::command::SelectPlayer;
 use crate::game_server::game_packet::{GamePacket, OpCode, Pos};
@@ -31,6 +34,12 @@ use super::lock_enforcer::{
 };
 use super::unique_guid::{zone_instance_guid, AMBIENT_NPC_DISCRIMINANT};
 
+// Position updates sent before the client applies a safety teleport are still below the kill plane
+const SAFETY_TELEPORT_GRACE: Duration = Duration::from_secs(1);
+
+// Players stuck falling may be teleported repeatedly, so explain the teleport only occasionally
+const SAFETY_MESSAGE_COOLDOWN: Duration = Duration::from_secs(30);
+
 #[derive(Clone, Deserialize)]
 pub struct Door {
     x: f32,
@@ -74,6 +83,18 @@ pub struct Transport {
     show_hover_description: bool,
 }
 
+#[derive(Deserialize)]
+struct SpawnPoint {
+    pos_x: f32,
+    pos_y: f32,
+    pos_z: f32,
+    pos_w: f32,
+    rot_x: f32,
+    rot_y: f32,
+    rot_z: f32,
+    rot_w: f32,
+}
+
 #[derive(Deserialize)]
 struct ZoneConfig {
     guid: u8,
@@ -92,6 +113,10 @@ struct ZoneConfig {
     spawn_rot_z: f32,
     spawn_rot_w: f32,
     spawn_sky: Option<String>,
+    #[serde(default)]
+    spawn_points: Vec<SpawnPoint>,
+    kill_plane_y: Option<f32>,
+    safety_message: Option<String>,
     speed: f32,
     jump_height_multiplier: f32,
     gravity_multiplier: f32,
@@ -140,6 +165,8 @@ impl NpcTemplate {
             interact_radius: self.interact_radius,
             auto_interact_radius: self.auto_interact_radius,
             instance_guid,
+            last_safety_teleport: None,
+            last_safety_message: None,
         }
     }
 }
@@ -155,6 +182,8 @@ pub struct Character {
     pub interact_radius: f32,
     pub auto_interact_radius: f32,
     pub instance_guid: u64,
+    pub last_safety_teleport: Option<Instant>,
+    pub last_safety_message: Option<Instant>,
 }
 
 impl IndexedGuid<u64, (u64, CharacterCategory)> for Character {
@@ -435,7 +464,10 @@ pub struct ZoneTemplate {
     pub asset_name: String,
     pub default_spawn_pos: Pos,
     pub default_spawn_rot: Pos,
+    spawn_points: Vec<(Pos, Pos)>,
     default_spawn_sky: String,
+    kill_plane_y: Option<f32>,
+    safety_message: Option<String>,
     pub speed: f32,
     pub jump_height_multiplier: f32,
     pub gravity_multiplier: f32,
@@ -490,7 +522,10 @@ impl ZoneTemplate {
             asset_name: self.asset_name.clone(),
             default_spawn_pos: self.default_spawn_pos,
             default_spawn_rot: self.default_spawn_rot,
+            spawn_points: self.spawn_points.clone(),
             default_spawn_sky: self.default_spawn_sky.clone(),
+            kill_plane_y: self.kill_plane_y,
+            safety_message: self.safety_message.clone(),
             speed: self.speed,
             jump_height_multiplier: self.jump_height_multiplier,
             gravity_multiplier: self.gravity_multiplier,
@@ -531,7 +566,10 @@ pub struct Zone {
     pub asset_name: String,
     pub default_spawn_pos: Pos,
     pub default_spawn_rot: Pos,
+    spawn_points: Vec<(Pos, Pos)>,
     default_spawn_sky: String,
+    kill_plane_y: Option<f32>,
+    safety_message: Option<String>,
     pub speed: f32,
     pub jump_height_multiplier: f32,
     pub gravity_multiplier: f32,
@@ -580,6 +618,16 @@ impl Zone {
         })?])
     }
 
+    // Pick a random spawn point so that players entering the zone at the same time don't stack
+    pub fn spawn_point(&self) -> (Pos, Pos) {
+        let index = rand::thread_rng().gen_range(0..=self.spawn_points.len());
+        if index == 0 {
+            (self.default_spawn_pos, self.default_spawn_rot)
+        } else {
+            self.spawn_points[index - 1]
+        }
+    }
+
     pub fn character_guids(
         guid: u64,
         characters_table_read_handle: &CharacterTableReadHandle,
@@ -595,30 +643,37 @@ impl Zone {
     }
 
     pub fn move_character(
+        sender: u32,
         pos_update: UpdatePlayerPosition,
         game_server: &GameServer,
     ) -> Result<Vec<Broadcast>, ProcessPacketError> {
-        let characters_to_interact =
-            game_server
-                .lock_enforcer()
-                .read_characters(|characters_table_read_handle| {
-                    let auto_interact_npcs = if let Some((instance_guid, _)) =
-                        characters_table_read_handle.index(pos_update.guid)
-                    {
-                        characters_table_read_handle
-                            .keys_by_index((
-                                instance_guid,
-                                CharacterCategory::NpcAutoInteractEnabled,
-                            ))
-                            .collect()
-                    } else {
-                        Vec::new()
-                    };
-
-                    CharacterLockRequest {
-                        read_guids: auto_interact_npcs.clone(),
-                        write_guids: vec![pos_update.guid],
-                        character_consumer: move |_, characters_read, mut characters_write, _| {
+        if pos_update.guid != player_guid(sender) {
+            println!(
+                "Player {} tried to move character {}",
+                sender, pos_update.guid
+            );
+            return Err(ProcessPacketError::CorruptedPacket);
+        }
+
+        let (characters_to_interact, safety_broadcasts) = game_server
+            .lock_enforcer()
+            .read_characters(|characters_table_read_handle| {
+                let possible_instance_guid = characters_table_read_handle
+                    .index(pos_update.guid)
+                    .map(|(instance_guid, _)| instance_guid);
+                let auto_interact_npcs = if let Some(instance_guid) = possible_instance_guid {
+                    characters_table_read_handle
+                        .keys_by_index((instance_guid, CharacterCategory::NpcAutoInteractEnabled))
+                        .collect()
+                } else {
+                    Vec::new()
+                };
+
+                CharacterLockRequest {
+                    read_guids: auto_interact_npcs.clone(),
+                    write_guids: vec![pos_update.guid],
+                    character_consumer:
+                        move |_, characters_read, mut characters_write, zones_lock_enforcer| {
                             if let Some(character_write_handle) =
                                 characters_write.get_mut(&pos_update.guid)
                             {
@@ -655,7 +710,45 @@ impl Zone {
                                     }
                                 }
 
-                                Ok(characters_to_interact)
+                                // Return players who fell out of the world to safety. Skip updates
+                                // the client sent before it received a recent teleport.
+                                let recently_teleported = character_write_handle
+                                    .last_safety_teleport
+                                    .is_some_and(|last_teleport| {
+                                        last_teleport.elapsed() < SAFETY_TELEPORT_GRACE
+                                    });
+                                let safety_broadcasts = match possible_instance_guid
+                                    .filter(|_| !recently_teleported)
+                                {
+                                    Some(instance_guid) => {
+                                        zones_lock_enforcer.read_zones(|_| ZoneLockRequest {
+                                            read_guids: vec![instance_guid],
+                                            write_guids: Vec::new(),
+                                            zone_consumer: |_, zones_read, _| {
+                                                if let Some(zone) = zones_read.get(&instance_guid) {
+                                                    let below_kill_plane = zone
+                                                        .kill_plane_y
+                                                        .is_some_and(|kill_plane_y| {
+                                                            character_write_handle.pos.y
+                                                                < kill_plane_y
+                                                        });
+                                                    if below_kill_plane {
+                                                        return teleport_to_safety(
+                                                            sender,
+                                                            character_write_handle,
+                                                            zone,
+                                                        );
+                                                    }
+                                                }
+
+                                                Ok(Vec::new())
+                                            },
+                                        })?
+                                    }
+                                    None => Vec::new(),
+                                };
+
+                                Ok((characters_to_interact, safety_broadcasts))
                             } else {
                                 println!(
                                     "Received position update from unknown character {}",
@@ -664,10 +757,11 @@ impl Zone {
                                 Err(ProcessPacketError::CorruptedPacket)
                             }
                         },
-                    }
-                })?;
+                }
+            })?;
+
+        let mut broadcasts = safety_broadcasts;
 
-        let mut broadcasts = Vec::new();
         for character_guid in characters_to_interact {
             let interact_request = SelectPlayer {
                 requester: pos_update.guid,
@@ -683,12 +777,22 @@ impl Zone {
 impl ZoneConfig {
     fn into_zones(
         self,
+        max_npcs: u16,
         global_characters_table: &mut GuidTableWriteHandle<
             u64,
             Character,
             (u64, CharacterCategory),
         >,
     ) -> (ZoneTemplate, Vec<Zone>) {
+        // NPC indices are 2 bytes in the character GUID, so the limit can be at most u16::MAX
+        let npc_count = self.doors.len() + self.transports.len();
+        if npc_count > max_npcs as usize {
+            panic!(
+                "Zone template {} has {} NPCs, but at most {} are allowed",
+                self.guid, npc_count, max_npcs
+            );
+        }
+
         let mut characters = Vec::new();
 
         let mut index = 0;
@@ -762,7 +866,29 @@ impl ZoneConfig {
                 z: self.spawn_rot_z,
                 w: self.spawn_rot_w,
             },
+            spawn_points: self
+                .spawn_points
+                .into_iter()
+                .map(|spawn_point| {
+                    (
+                        Pos {
+                            x: spawn_point.pos_x,
+                            y: spawn_point.pos_y,
+                            z: spawn_point.pos_z,
+                            w: spawn_point.pos_w,
+                        },
+                        Pos {
+                            x: spawn_point.rot_x,
+                            y: spawn_point.rot_y,
+                            z: spawn_point.rot_z,
+                            w: spawn_point.rot_w,
+                        },
+                    )
+                })
+                .collect(),
             default_spawn_sky: self.spawn_sky.clone().unwrap_or("".to_string()),
+            kill_plane_y: self.kill_plane_y,
+            safety_message: self.safety_message.clone(),
             speed: self.speed,
             jump_height_multiplier: self.jump_height_multiplier,
             gravity_multiplier: self.gravity_multiplier,
@@ -785,6 +911,7 @@ impl ZoneConfig {
 type ZoneTemplateMap = BTreeMap<u8, ZoneTemplate>;
 pub fn load_zones(
     config_dir: &Path,
+    max_npcs_per_zone: u16,
     mut global_characters_table: GuidTableWriteHandle<u64, Character, (u64, CharacterCategory)>,
 ) -> Result<(ZoneTemplateMap, GuidTable<u64, Zone, u8>), Error> {
     let mut file = File::open(config_dir.join("zones.json"))?;
@@ -795,7 +922,8 @@ pub fn load_zones(
     {
         let mut zones_write_handle = zones.write();
         for zone_config in zone_configs {
-            let (template, zones) = zone_config.into_zones(&mut global_characters_table);
+            let (template, zones) =
+                zone_config.into_zones(max_npcs_per_zone, &mut global_characters_table);
             let template_guid = Guid::guid(&template);
 
             if templates.insert(template_guid, template).is_some() {
@@ -821,8 +949,9 @@ pub fn enter_zone(
     destination_pos: Option<Pos>,
     destination_rot: Option<Pos>,
 ) -> Result<Vec<Broadcast>, ProcessPacketError> {
-    let destination_pos = destination_pos.unwrap_or(destination_read_handle.default_spawn_pos);
-    let destination_rot = destination_rot.unwrap_or(destination_read_handle.default_spawn_rot);
+    let (spawn_pos, spawn_rot) = destination_read_handle.spawn_point();
+    let destination_pos = destination_pos.unwrap_or(spawn_pos);
+    let destination_rot = destination_rot.unwrap_or(spawn_rot);
 
     let character = characters_table_write_handle.remove(player_guid(player));
     if let Some((character, (_, character_category))) = character {
@@ -1079,6 +1208,38 @@ pub fn teleport_within_zone(
     )])
 }
 
+pub fn teleport_to_safety(
+    sender: u32,
+    character: &mut Character,
+    zone: &Zone,
+) -> Result<Vec<Broadcast>, ProcessPacketError> {
+    let (spawn_pos, spawn_rot) = zone.spawn_point();
+    let now = Instant::now();
+    character.pos = spawn_pos;
+    character.rot = spawn_rot;
+    character.last_safety_teleport = Some(now);
+
+    let mut broadcasts = teleport_within_zone(sender, spawn_pos, spawn_rot)?;
+
+    if let Some(message) = &zone.safety_message {
+        let recently_notified = character
+            .last_safety_message
+            .is_some_and(|last_message| now.duration_since(last_message) < SAFETY_MESSAGE_COOLDOWN);
+        if !recently_notified {
+            character.last_safety_message = Some(now);
+            broadcasts.push(Broadcast::Single(
+                sender,
+                vec![GamePacket::serialize(&TunneledPacket {
+                    unknown1: true,
+                    inner: SendMessage::System(MessagePayload::system(message.clone())),
+                })?],
+            ));
+        }
+    }
+
+    Ok(broadcasts)
+}
+
 #[derive(SerializePacket, DeserializePacket)]
 pub struct ZoneTeleportRequest {
     pub destination_guid: u32,
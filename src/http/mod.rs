@@ -2,9 +2,11 @@ use std::collections::{BTreeMap, VecDeque};
 use std::ffi::{OsStr, OsString};
 use std::path::{Component, PathBuf};
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 
 use axum::extract::{Path, State};
-use axum::http::StatusCode;
+use axum::http::{header, StatusCode};
+use axum::response::{IntoResponse, Response};
 use axum::routing::get;
 use axum::{serve, Router};
 use miniz_oxide::deflate::compress_to_vec_zlib;
@@ -13,12 +15,15 @@ use tokio::fs::{create_dir_all, read, read_dir, remove_dir_all, write, OpenOptio
 use tokio::io;
 use tokio::io::{AsyncReadExt, AsyncWriteExt};
 use tokio::net::TcpListener;
+use tokio::sync::Semaphore;
 
 const COMPRESSED_MAGIC: u32 = 0xa1b2c3d4;
 const ZLIB_COMPRESSION_LEVEL: u8 = 6;
 const COMPRESSED_EXTENSION: &str = "z";
 const CRC_EXTENSION_SEPARATOR: &str = "_";
 const MANIFEST_NAME: &str = "manifest.txt";
+const RETRY_AFTER_SECONDS: &str = "1";
+const PROGRESS_LOG_INTERVAL: Duration = Duration::from_secs(5);
 
 struct Manifest {
     name: OsString,
@@ -128,10 +133,24 @@ async fn prepare_asset_cache(
     create_dir_all(assets_cache_path).await?;
     let mut asset_paths = list_files(assets_path).await?;
     asset_paths.sort();
+    let total_assets = asset_paths.len();
+    println!("Preparing asset cache for {} assets", total_assets);
 
     let mut crc_map = CrcMap::new();
+    let mut total_bytes_written = 0;
+    let mut matching_assets_by_manifest = vec![0; manifests.len()];
+    let mut last_progress_log = Instant::now();
+
+    for (index, asset_path) in asset_paths.into_iter().enumerate() {
+        // Log periodically rather than per file so large asset folders don't flood the log
+        if last_progress_log.elapsed() >= PROGRESS_LOG_INTERVAL {
+            println!(
+                "Prepared {}/{} assets ({} compressed bytes)",
+                index, total_assets, total_bytes_written
+            );
+            last_progress_log = Instant::now();
+        }
 
-    for asset_path in asset_paths {
         let contents = read(&asset_path).await?;
         let compressed_asset_name = compressed_asset_name(&asset_path, assets_path);
         let bytes_written = write_to_cache(
@@ -141,6 +160,13 @@ async fn prepare_asset_cache(
             &mut crc_map,
         )
         .await?;
+        total_bytes_written += bytes_written;
+
+        for (manifest, matching_assets) in manifests.iter().zip(&mut matching_assets_by_manifest) {
+            if compressed_asset_name.starts_with(&manifest.prefix) {
+                *matching_assets += 1;
+            }
+        }
 
         // Determine which manifest this file belongs to, if any
         let manifest =
@@ -185,8 +211,21 @@ async fn prepare_asset_cache(
         }
     }
 
+    println!(
+        "Prepared {}/{} assets ({} compressed bytes)",
+        total_assets, total_assets, total_bytes_written
+    );
+
     // Compress manifest and create CRC file
-    for manifest in manifests {
+    for (manifest, matching_assets) in manifests.iter().zip(matching_assets_by_manifest) {
+        // Placeholder folders may not have assets yet, but this usually means a typo'd prefix
+        if matching_assets == 0 {
+            println!(
+                "Warning: manifest prefix {} matches no assets",
+                manifest.prefix.display()
+            );
+        }
+
         create_dir_all(assets_cache_path.join(&manifest.prefix)).await?;
         let manifest_asset_name = &manifest.prefix.join(&manifest.name);
         let mut manifest_file = OpenOptions::new()
@@ -303,10 +342,20 @@ fn is_name_hash(component: &OsStr) -> bool {
         }
 }
 
+type AssetState = (Arc<PathBuf>, Arc<CrcMap>, Arc<Semaphore>);
 async fn asset_handler(
     Path(asset): Path<PathBuf>,
-    State((assets_cache_path, crc_map)): State<(Arc<PathBuf>, Arc<CrcMap>)>,
-) -> Result<Vec<u8>, StatusCode> {
+    State((assets_cache_path, crc_map, request_permits)): State<AssetState>,
+) -> Response {
+    // Hold the permit until the asset is read so that the number of assets in memory is bounded
+    let Ok(_permit) = request_permits.try_acquire() else {
+        return (
+            StatusCode::SERVICE_UNAVAILABLE,
+            [(header::RETRY_AFTER, RETRY_AFTER_SECONDS)],
+        )
+            .into_response();
+    };
+
     let is_first_component_name_hash = asset.iter().next().map(is_name_hash).unwrap_or(false);
 
     // Ignore the name hash if it is included
@@ -318,7 +367,9 @@ async fn asset_handler(
         asset
     };
 
-    retrieve_asset(asset_name, assets_cache_path, crc_map).await
+    retrieve_asset(asset_name, assets_cache_path, crc_map)
+        .await
+        .into_response()
 }
 
 async fn try_start(
@@ -326,6 +377,7 @@ async fn try_start(
     config_dir: &std::path::Path,
     assets_path: &std::path::Path,
     assets_cache_path: PathBuf,
+    max_concurrent_requests: usize,
 ) -> io::Result<()> {
     let manifests = read_manifests_config(config_dir).await?;
     let crc_map = prepare_asset_cache(assets_path, &assets_cache_path, &manifests).await?;
@@ -333,7 +385,11 @@ async fn try_start(
     let listener = TcpListener::bind(format!("127.0.0.1:{}", port)).await?;
     let app: Router<()> = Router::new()
         .route("/assets/*asset", get(asset_handler))
-        .with_state((Arc::new(assets_cache_path), Arc::new(crc_map)));
+        .with_state((
+            Arc::new(assets_cache_path),
+            Arc::new(crc_map),
+            Arc::new(Semaphore::new(max_concurrent_requests)),
+        ));
 
     serve(listener, app).await
 }
@@ -343,8 +399,15 @@ pub async fn start(
     config_dir: &std::path::Path,
     assets_path: &std::path::Path,
     assets_cache_path: PathBuf,
+    max_concurrent_requests: usize,
 ) {
-    try_start(port, config_dir, assets_path, assets_cache_path)
-        .await
-        .expect("Unable to start HTTP server");
+    try_start(
+        port,
+        config_dir,
+        assets_path,
+        assets_cache_path,
+        max_concurrent_requests,
+    )
+    .await
+    .expect("Unable to start HTTP server");
 }
@@ -7,7 +7,7 @@ use tokio::spawn;
 
 use crate::channel_manager::{ChannelManager, ReceiveResult};
 use crate::game_server::GameServer;
-use crate::protocol::Channel;
+use crate::protocol::{Channel, MAX_BUFFER_SIZE};
 
 mod channel_manager;
 mod game_server;
@@ -17,11 +17,13 @@ mod protocol;
 #[tokio::main]
 async fn main() {
     let config_dir = Path::new("config");
+    let max_concurrent_asset_requests = 64;
     spawn(http::start(
         4000,
         config_dir,
         Path::new("config/custom_assets"),
         PathBuf::from(".asset_cache"),
+        max_concurrent_asset_requests,
     ));
     println!("Hello, world!");
     let socket = UdpSocket::bind(SocketAddr::new(
@@ -32,11 +34,29 @@ async fn main() {
 
     let channel_manager = RwLock::new(ChannelManager::new());
 
-    let game_server = GameServer::new(config_dir).unwrap();
+    // Maximum chat message length in bytes
+    let max_chat_message_length = 256;
+
+    let max_npcs_per_zone = 1024u16;
+
+    let game_server =
+        GameServer::new(config_dir, max_npcs_per_zone, max_chat_message_length).unwrap();
     let process_delta = 40u8;
     let send_delta = 20u8;
+
+    // Small enough buffers would leave no room for data after the packet header and footer, so
+    // session requests below this size are rejected
+    let min_buffer_size = 64;
+
+    // Clients only send small messages, so larger fragmented messages are treated as malicious
+    let max_fragmented_data_size = 1u32 << 20;
+
+    // A message the size of one buffer may be split into at most this many fragments. Clients
+    // with a smaller buffer size are allowed proportionally more fragments for the same data.
+    let max_fragments_per_buffer = 4u32;
+
     loop {
-        let mut buf = [0; 512];
+        let mut buf = [0; MAX_BUFFER_SIZE as usize];
         if let Ok((len, src)) = socket.recv_from(&mut buf) {
             //println!("Bytes received: {}", len);
             let recv_data = &buf[0..len];
@@ -48,9 +68,17 @@ async fn main() {
             if receive_result == ReceiveResult::CreateChannelFirst {
                 println!("Creating channel for {}", src);
                 drop(read_handle);
-                let previous_channel = channel_manager
-                    .write()
-                    .insert(&src, Channel::new(200, 1000, 5));
+                let previous_channel = channel_manager.write().insert(
+                    &src,
+                    Channel::new(
+                        200,
+                        min_buffer_size,
+                        1000,
+                        5,
+                        max_fragmented_data_size,
+                        max_fragments_per_buffer,
+                    ),
+                );
                 read_handle = channel_manager.read();
 
                 if previous_channel.is_some() {
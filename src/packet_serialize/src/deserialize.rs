@@ -1,4 +1,4 @@
-use crate::NullTerminatedString;
+use crate::{BitFlags, NullTerminatedString, NullableVec};
 use byteorder::{LittleEndian, ReadBytesExt};
 use std::io::{BufRead, Cursor, Error, Read};
 use std::string::FromUtf8Error;
@@ -10,6 +10,7 @@ pub enum DeserializePacketError {
     InvalidString(FromUtf8Error),
     MissingNullTerminator,
     UnknownDiscriminator,
+    TrailingBytes(u64),
 }
 
 impl From<Error> for DeserializePacketError {
@@ -150,3 +151,34 @@ impl<T: DeserializePacket> DeserializePacket for Vec<T> {
         Ok(items)
     }
 }
+
+impl<T: DeserializePacket> DeserializePacket for NullableVec<T> {
+    fn deserialize(cursor: &mut Cursor<&[u8]>) -> Result<NullableVec<T>, DeserializePacketError> {
+        let length = cursor.read_i32::<LittleEndian>()?;
+        if length < 0 {
+            return Ok(NullableVec(None));
+        }
+
+        let mut items = Vec::new();
+        for _ in 0..length {
+            let item: T = DeserializePacket::deserialize(cursor)?;
+            items.push(item);
+        }
+
+        Ok(NullableVec(Some(items)))
+    }
+}
+
+impl<const N: usize> DeserializePacket for BitFlags<N> {
+    fn deserialize(cursor: &mut Cursor<&[u8]>) -> Result<BitFlags<N>, DeserializePacketError> {
+        let mut flags = [false; N];
+        for flag_chunk in flags.chunks_mut(8) {
+            let byte = cursor.read_u8()?;
+            for (bit, flag) in flag_chunk.iter_mut().enumerate() {
+                *flag = (byte >> bit) & 1 != 0;
+            }
+        }
+
+        Ok(BitFlags(flags))
+    }
+}
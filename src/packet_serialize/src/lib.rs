@@ -2,9 +2,117 @@ mod deserialize;
 mod serialize;
 
 pub use deserialize::*;
-pub use packet_serialize_derive::{DeserializePacket, SerializePacket};
+/// Unknown `packet(...)` keys are rejected at compile time:
+///
+/// ```compile_fail
+/// use packet_serialize::DeserializePacket;
+///
+/// #[derive(DeserializePacket)]
+/// #[packet(unknown)]
+/// struct Packet {
+///     value: u32,
+/// }
+/// ```
+pub use packet_serialize_derive::DeserializePacket;
+pub use packet_serialize_derive::SerializePacket;
 pub use serialize::*;
 
 pub struct LengthlessVec<T>(pub Vec<T>);
 
 pub struct NullTerminatedString(pub String);
+
+// Vector prefixed by a signed length, where a negative length means the vector is absent.
+// An empty vector is distinct from an absent vector and is serialized with length 0.
+#[derive(Debug, PartialEq)]
+pub struct NullableVec<T>(pub Option<Vec<T>>);
+
+// Booleans packed into bytes, least significant bit first. The last byte is padded with zeros
+// when the number of flags is not a multiple of 8.
+#[derive(Debug, PartialEq)]
+pub struct BitFlags<const N: usize>(pub [bool; N]);
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+
+    fn round_trip(vec: &NullableVec<u8>) -> (Vec<u8>, NullableVec<u8>) {
+        let mut buffer = Vec::new();
+        vec.serialize(&mut buffer).unwrap();
+        let deserialized = NullableVec::deserialize(&mut Cursor::new(&buffer[..])).unwrap();
+        (buffer, deserialized)
+    }
+
+    #[test]
+    fn test_bit_flags_partial_byte() {
+        let flags = BitFlags([true, false, true, true, false, false, true]);
+        let mut buffer = Vec::new();
+        flags.serialize(&mut buffer).unwrap();
+        assert_eq!(buffer, vec![0b0100_1101]);
+
+        let deserialized = BitFlags::deserialize(&mut Cursor::new(&buffer[..])).unwrap();
+        assert_eq!(deserialized, flags);
+    }
+
+    #[test]
+    fn test_bit_flags_multiple_bytes() {
+        let flags = BitFlags([
+            false, false, false, false, false, false, false, true, true, false,
+        ]);
+        let mut buffer = Vec::new();
+        flags.serialize(&mut buffer).unwrap();
+        assert_eq!(buffer, vec![0b1000_0000, 0b0000_0001]);
+
+        let deserialized = BitFlags::deserialize(&mut Cursor::new(&buffer[..])).unwrap();
+        assert_eq!(deserialized, flags);
+    }
+
+    #[test]
+    fn test_bit_flags_ignores_padding() {
+        let buffer = [0b1111_1110];
+        let deserialized: BitFlags<3> =
+            BitFlags::deserialize(&mut Cursor::new(&buffer[..])).unwrap();
+        assert_eq!(deserialized, BitFlags([false, true, true]));
+    }
+
+    #[test]
+    fn test_bit_flags_missing_byte() {
+        let buffer = [0b1111_1111];
+        let result: Result<BitFlags<9>, DeserializePacketError> =
+            BitFlags::deserialize(&mut Cursor::new(&buffer[..]));
+        assert!(matches!(result, Err(DeserializePacketError::IoError(_))));
+    }
+
+    #[test]
+    fn test_nullable_vec_none() {
+        let vec = NullableVec(None);
+        let (buffer, deserialized) = round_trip(&vec);
+        assert_eq!(buffer, vec![0xff, 0xff, 0xff, 0xff]);
+        assert_eq!(deserialized, vec);
+    }
+
+    #[test]
+    fn test_nullable_vec_empty() {
+        let vec = NullableVec(Some(Vec::new()));
+        let (buffer, deserialized) = round_trip(&vec);
+        assert_eq!(buffer, vec![0, 0, 0, 0]);
+        assert_eq!(deserialized, vec);
+    }
+
+    #[test]
+    fn test_nullable_vec_some() {
+        let vec = NullableVec(Some(vec![1, 2, 3]));
+        let (buffer, deserialized) = round_trip(&vec);
+        assert_eq!(buffer, vec![3, 0, 0, 0, 1, 2, 3]);
+        assert_eq!(deserialized, vec);
+    }
+
+    #[test]
+    fn test_nullable_vec_any_negative_length_is_none() {
+        let buffer = (-5i32).to_le_bytes();
+        let deserialized: NullableVec<u8> =
+            NullableVec::deserialize(&mut Cursor::new(&buffer[..])).unwrap();
+        assert_eq!(deserialized, NullableVec(None));
+    }
+}
@@ -1,4 +1,4 @@
-use crate::{LengthlessVec, NullTerminatedString};
+use crate::{BitFlags, LengthlessVec, NullTerminatedString, NullableVec};
 use byteorder::{LittleEndian, WriteBytesExt};
 use std::io::{Error, Write};
 
@@ -150,3 +150,32 @@ impl<T: SerializePacket> SerializePacket for LengthlessVec<T> {
         Ok(())
     }
 }
+
+impl<T: SerializePacket> SerializePacket for NullableVec<T> {
+    fn serialize(&self, buffer: &mut Vec<u8>) -> Result<(), SerializePacketError> {
+        if let Some(inner_vec) = &self.0 {
+            buffer.write_i32::<LittleEndian>(inner_vec.len() as i32)?;
+            for item in inner_vec {
+                SerializePacket::serialize(item, buffer)?;
+            }
+        } else {
+            buffer.write_i32::<LittleEndian>(-1)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl<const N: usize> SerializePacket for BitFlags<N> {
+    fn serialize(&self, buffer: &mut Vec<u8>) -> Result<(), SerializePacketError> {
+        for flags in self.0.chunks(8) {
+            let byte = flags
+                .iter()
+                .enumerate()
+                .fold(0u8, |byte, (bit, flag)| byte | ((*flag as u8) << bit));
+            buffer.write_u8(byte)?;
+        }
+
+        Ok(())
+    }
+}
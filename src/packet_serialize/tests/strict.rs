@@ -0,0 +1,38 @@
+use std::io::Cursor;
+
+use packet_serialize::{DeserializePacket, DeserializePacketError};
+
+#[derive(DeserializePacket)]
+#[packet(strict)]
+struct StrictPacket {
+    value: u32,
+}
+
+#[derive(DeserializePacket)]
+struct LenientPacket {
+    value: u32,
+}
+
+#[test]
+fn test_strict_packet_rejects_trailing_bytes() {
+    let buffer = [1, 0, 0, 0, 2, 3];
+    let result = StrictPacket::deserialize(&mut Cursor::new(&buffer[..]));
+    assert!(matches!(
+        result,
+        Err(DeserializePacketError::TrailingBytes(2))
+    ));
+}
+
+#[test]
+fn test_strict_packet_accepts_exact_bytes() {
+    let buffer = [1, 0, 0, 0];
+    let packet = StrictPacket::deserialize(&mut Cursor::new(&buffer[..])).unwrap();
+    assert_eq!(packet.value, 1);
+}
+
+#[test]
+fn test_lenient_packet_accepts_trailing_bytes() {
+    let buffer = [1, 0, 0, 0, 2, 3];
+    let packet = LenientPacket::deserialize(&mut Cursor::new(&buffer[..])).unwrap();
+    assert_eq!(packet.value, 1);
+}
@@ -1,7 +1,24 @@
 use proc_macro2::TokenStream;
 use quote::{quote, quote_spanned};
 use syn::spanned::Spanned;
-use syn::{parse_quote, Data, Fields, GenericParam, Generics, Index};
+use syn::{parse_quote, Attribute, Data, Fields, GenericParam, Generics, Index};
+
+// Strict packets must be the last thing in the buffer, so only use them for top-level packets
+pub fn is_strict(attrs: &[Attribute]) -> syn::Result<bool> {
+    let mut strict = false;
+    for attr in attrs.iter().filter(|attr| attr.path().is_ident("packet")) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("strict") {
+                strict = true;
+                Ok(())
+            } else {
+                Err(meta.error("unknown packet attribute"))
+            }
+        })?;
+    }
+
+    Ok(strict)
+}
 
 pub fn add_trait_bounds(mut generics: Generics) -> Generics {
     for param in &mut generics.params {
@@ -28,23 +28,39 @@ pub fn derive_serialize(input: proc_macro::TokenStream) -> proc_macro::TokenStre
     proc_macro::TokenStream::from(expanded)
 }
 
-#[proc_macro_derive(DeserializePacket)]
+#[proc_macro_derive(DeserializePacket, attributes(packet))]
 pub fn derive_deserialize(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
     let input = parse_macro_input!(input as DeriveInput);
 
+    let strict = match deserialize::is_strict(&input.attrs) {
+        Ok(strict) => strict,
+        Err(err) => return err.to_compile_error().into(),
+    };
     let name = input.ident;
 
     let generics = deserialize::add_trait_bounds(input.generics);
     let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
 
     let assignments = deserialize::assign_fields(&input.data);
+    let trailing_bytes_check = if strict {
+        quote! {
+            let trailing_bytes = (cursor.get_ref().len() as u64).saturating_sub(cursor.position());
+            if trailing_bytes > 0 {
+                return Err(packet_serialize::DeserializePacketError::TrailingBytes(trailing_bytes));
+            }
+        }
+    } else {
+        quote!()
+    };
 
     let expanded = quote! {
         impl #impl_generics packet_serialize::DeserializePacket for #name #ty_generics #where_clause {
             fn deserialize(cursor: &mut std::io::Cursor<&[u8]>) -> Result<Self, packet_serialize::DeserializePacketError> {
-                Ok(#name {
+                let value = #name {
                     #assignments
-                })
+                };
+                #trailing_bytes_check
+                Ok(value)
             }
         }
     };
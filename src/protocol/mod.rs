@@ -58,6 +58,15 @@ pub type SessionId = u32;
 pub type BufferSize = u32;
 pub type ApplicationProtocol = String;
 
+// The server's own receive buffer size. Clients requesting a larger buffer are clamped to it.
+pub const MAX_BUFFER_SIZE: BufferSize = 512;
+
+// Limit replies so that spoofed packets cannot make the server send a reply for every packet
+const MILLIS_BETWEEN_UNKNOWN_SENDER_REPLIES: u128 = 1000;
+
+// After logging a bad packet, later ones in this window are only counted and summarized
+const MILLIS_BETWEEN_BAD_PACKET_LOGS: u128 = 60000;
+
 #[derive(Copy, Clone, Debug, Eq, PartialEq)]
 pub enum DisconnectReason {
     Unknown = 0,
@@ -200,6 +209,7 @@ pub struct Session {
 pub struct Channel {
     session: Option<Session>,
     buffer_size: BufferSize,
+    min_buffer_size: BufferSize,
     recency_limit: SequenceNumber,
     millis_until_resend: u128,
     fragment_state: FragmentState,
@@ -209,31 +219,51 @@ pub struct Channel {
     next_client_sequence: SequenceNumber,
     next_server_sequence: SequenceNumber,
     last_server_ack: SequenceNumber,
+    last_unknown_sender_reply: u128,
+    last_bad_packet_log: u128,
+    suppressed_bad_packets: u32,
 }
 
 impl Channel {
     pub fn new(
         initial_buffer_size: BufferSize,
+        min_buffer_size: BufferSize,
         recency_limit: SequenceNumber,
         millis_until_resend: u128,
+        max_fragmented_data_size: u32,
+        max_fragments_per_buffer: u32,
     ) -> Self {
         Channel {
             session: None,
             buffer_size: initial_buffer_size,
+            min_buffer_size,
             recency_limit,
             millis_until_resend,
-            fragment_state: FragmentState::new(),
+            fragment_state: FragmentState::new(max_fragmented_data_size, max_fragments_per_buffer),
             send_queue: VecDeque::new(),
             receive_queue: VecDeque::new(),
             reordered_packets: BTreeMap::new(),
             next_client_sequence: 0,
             next_server_sequence: 0,
             last_server_ack: 0,
+            last_unknown_sender_reply: 0,
+            last_bad_packet_log: 0,
+            suppressed_bad_packets: 0,
         }
     }
 
     pub fn receive(&mut self, data: &[u8]) -> Result<u32, DeserializeError> {
-        let mut packets = deserialize_packet(data, &self.session)?;
+        let result = deserialize_packet(data, &self.session);
+
+        // Tell clients with a stale session to reconnect instead of silently dropping their packets.
+        // Spoofed senders can trigger this for every packet, so the log is rate limited too.
+        if let Err(DeserializeError::MissingSession(op_code)) = result {
+            self.reply_unknown_sender();
+            self.log_bad_packet(|| format!("Received {:?} packet without a session", op_code));
+            return Ok(0);
+        }
+
+        let mut packets = result?;
 
         let packet_count = packets.len() as u32;
         packets
@@ -243,6 +273,8 @@ impl Channel {
     }
 
     pub fn process_next(&mut self, count: u8) -> Vec<Vec<u8>> {
+        self.flush_bad_packet_summary();
+
         let mut needs_new_ack = false;
         let mut packets_to_process = Vec::new();
 
@@ -275,13 +307,15 @@ impl Channel {
                     }
                 }
 
-                match self.fragment_state.add(packet) {
+                match self.fragment_state.add(packet, self.buffer_size) {
                     Ok(possible_packet) => {
                         if let Some(packet) = possible_packet {
                             packets_to_process.push(packet);
                         }
                     }
-                    Err(err) => println!("Unable to process packet: {:?}", err),
+                    Err(err) => {
+                        self.log_bad_packet(|| format!("Unable to process packet: {:?}", err))
+                    }
                 }
             } else {
                 break;
@@ -303,7 +337,7 @@ impl Channel {
                 if let Ok(mut unbundled_packets) = unbundle_reliable_data(&data) {
                     packets.append(&mut unbundled_packets);
                 } else {
-                    println!("Bad bundled packet");
+                    self.log_bad_packet(|| "Bad bundled packet".to_string());
                 }
             }
         }
@@ -328,6 +362,8 @@ impl Channel {
     }
 
     pub fn send_next(&mut self, count: u8) -> Result<Vec<Vec<u8>>, SerializeError> {
+        self.flush_bad_packet_summary();
+
         let mut indices_to_send = Vec::new();
 
         // If the packet was acked, it was already sent, so don't send it again
@@ -419,16 +455,48 @@ impl Channel {
         buffer_size: BufferSize,
         app_protocol: &ApplicationProtocol,
     ) {
-        // TODO: disallow session overwrite
-        let session = Session {
-            session_id,
-            crc_length: 3,
-            crc_seed: random::<CrcSeed>(),
-            allow_compression: true,
-            use_encryption: false,
+        if buffer_size < self.min_buffer_size {
+            println!(
+                "Rejected session {} with buffer size {} below minimum {}",
+                session_id, buffer_size, self.min_buffer_size
+            );
+            return;
+        }
+
+        // A retransmitted request means the client missed our reply, so resend the same parameters
+        let session = match self.session.take() {
+            Some(existing_session) if existing_session.session_id == session_id => {
+                println!("Resending reply for session {}", session_id);
+                existing_session
+            }
+            previous_session => {
+                if let Some(previous_session) = previous_session {
+                    println!(
+                        "Session {} replaced by new session {}",
+                        previous_session.session_id, session_id
+                    );
+                    self.reset_connection_state();
+                }
+
+                Session {
+                    session_id,
+                    crc_length: 3,
+                    crc_seed: random::<CrcSeed>(),
+                    allow_compression: true,
+                    use_encryption: false,
+                }
+            }
         };
 
-        self.buffer_size = buffer_size;
+        let clamped_buffer_size = buffer_size.min(MAX_BUFFER_SIZE);
+        if clamped_buffer_size != buffer_size {
+            println!(
+                "Clamped requested buffer size {} to {}",
+                buffer_size, clamped_buffer_size
+            );
+        }
+
+        self.buffer_size = clamped_buffer_size;
         self.send_queue
             .push_back(PendingPacket::new(Packet::SessionReply(
                 session_id,
@@ -436,12 +504,62 @@ impl Channel {
                 session.crc_length,
                 session.allow_compression,
                 session.use_encryption,
-                512,
+                MAX_BUFFER_SIZE,
                 3,
             )));
         self.session = Some(session);
     }
 
+    fn reset_connection_state(&mut self) {
+        self.fragment_state.reset();
+        self.send_queue.clear();
+        self.receive_queue.clear();
+        self.reordered_packets.clear();
+        self.next_client_sequence = 0;
+        self.next_server_sequence = 0;
+        self.last_server_ack = 0;
+    }
+
+    fn log_bad_packet(&mut self, message: impl FnOnce() -> String) {
+        self.flush_bad_packet_summary();
+
+        let now = PendingPacket::now();
+        if now.saturating_sub(self.last_bad_packet_log) < MILLIS_BETWEEN_BAD_PACKET_LOGS {
+            self.suppressed_bad_packets = self.suppressed_bad_packets.saturating_add(1);
+            return;
+        }
+
+        println!("{}", message());
+        self.last_bad_packet_log = now;
+    }
+
+    fn flush_bad_packet_summary(&mut self) {
+        if self.suppressed_bad_packets == 0 {
+            return;
+        }
+
+        let now = PendingPacket::now();
+        if now.saturating_sub(self.last_bad_packet_log) >= MILLIS_BETWEEN_BAD_PACKET_LOGS {
+            println!(
+                "{} more bad packets within {} seconds of the last one logged",
+                self.suppressed_bad_packets,
+                MILLIS_BETWEEN_BAD_PACKET_LOGS / 1000
+            );
+            self.suppressed_bad_packets = 0;
+        }
+    }
+
+    fn reply_unknown_sender(&mut self) {
+        let now = PendingPacket::now();
+        if now.saturating_sub(self.last_unknown_sender_reply)
+            >= MILLIS_BETWEEN_UNKNOWN_SENDER_REPLIES
+        {
+            self.last_unknown_sender_reply = now;
+            self.send_queue
+                .push_back(PendingPacket::new(Packet::UnknownSender));
+        }
+    }
+
     fn process_heartbeat(&mut self) {
         self.send_queue
             .push_back(PendingPacket::new(Packet::Heartbeat));
@@ -12,6 +12,8 @@ pub enum DataError {
     MissingDataLength,
     ExpectedFragment(ProtocolOpCode),
     BadSubPacketLength,
+    TooManyFragments(u32),
+    DataTooLarge(u32),
 }
 
 impl From<Error> for DataError {
@@ -26,21 +28,44 @@ pub enum DataPacket {
 }
 
 pub struct FragmentState {
+    max_data_size: u32,
+    max_fragments_per_buffer: u32,
     buffer: Vec<u8>,
     remaining_bytes: u32,
+    fragment_limit: u32,
+    remaining_fragments: u32,
+    discarding: bool,
 }
 
 impl FragmentState {
-    pub fn new() -> Self {
+    pub fn new(max_data_size: u32, max_fragments_per_buffer: u32) -> Self {
         FragmentState {
+            max_data_size,
+            max_fragments_per_buffer,
             buffer: Vec::new(),
             remaining_bytes: 0,
+            fragment_limit: 0,
+            remaining_fragments: 0,
+            discarding: false,
         }
     }
 
-    pub fn add(&mut self, packet: Packet) -> Result<Option<Packet>, DataError> {
+    pub fn reset(&mut self) {
+        self.buffer.clear();
+        self.remaining_bytes = 0;
+        self.fragment_limit = 0;
+        self.remaining_fragments = 0;
+        self.discarding = false;
+    }
+
+    pub fn add(
+        &mut self,
+        packet: Packet,
+        buffer_size: BufferSize,
+    ) -> Result<Option<Packet>, DataError> {
         if let Packet::DataFragment(sequence_number, data) = packet {
             let packet_data;
+            let mut declared_bytes = None;
             if self.remaining_bytes == 0 {
                 if data.len() < 8 {
                     return Err(DataError::MissingDataLength);
@@ -48,22 +73,51 @@ impl FragmentState {
 
                 packet_data = &data[4..];
                 self.remaining_bytes = Cursor::new(&data).read_u32::<BigEndian>()?;
+                self.discarding = false;
+                declared_bytes = Some(self.remaining_bytes);
+
+                // Clients with a smaller buffer size need more fragments to send the same data
+                let min_fragment_size = (buffer_size / self.max_fragments_per_buffer).max(1);
+                self.fragment_limit = self.remaining_bytes.div_ceil(min_fragment_size).max(1);
+                self.remaining_fragments = self.fragment_limit;
             } else {
                 packet_data = &data;
             }
 
+            // Consume the fragment even if the message is rejected, so the rest of the message
+            // isn't mistaken for the start of a new one
             self.remaining_bytes = self
                 .remaining_bytes
                 .saturating_sub(packet_data.len() as u32);
+
+            if self.discarding {
+                return Ok(None);
+            }
+
+            if let Some(declared_bytes) = declared_bytes {
+                if declared_bytes > self.max_data_size {
+                    self.discarding = true;
+                    return Err(DataError::DataTooLarge(declared_bytes));
+                }
+            }
+
+            if self.remaining_fragments == 0 {
+                self.buffer.clear();
+                self.discarding = true;
+                return Err(DataError::TooManyFragments(self.fragment_limit));
+            }
+            self.remaining_fragments -= 1;
+
             self.buffer.extend(packet_data);
 
             if self.remaining_bytes > 0 {
                 return Ok(None);
             }
 
-            let old_buffer = self.buffer.clone();
-            self.buffer.clear();
-            return Ok(Some(Packet::Data(sequence_number, old_buffer)));
+            return Ok(Some(Packet::Data(
+                sequence_number,
+                std::mem::take(&mut self.buffer),
+            )));
         }
 
         if self.remaining_bytes > 0 {
@@ -154,3 +208,78 @@ pub fn fragment_data(
         Err(DataError::MissingSession)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MAX_DATA_SIZE: u32 = 1 << 20;
+    const MAX_FRAGMENTS_PER_BUFFER: u32 = 4;
+
+    fn first_fragment(sequence_number: u16, total_bytes: u32, payload: &[u8]) -> Packet {
+        let mut data = Vec::new();
+        data.write_u32::<BigEndian>(total_bytes).unwrap();
+        data.extend(payload);
+        Packet::DataFragment(sequence_number, data)
+    }
+
+    #[test]
+    fn test_fragments_at_limit_are_reassembled() {
+        // A 64-byte buffer allows 16-byte fragments on average, so 32 bytes may span 2 fragments
+        let mut state = FragmentState::new(MAX_DATA_SIZE, MAX_FRAGMENTS_PER_BUFFER);
+        assert!(matches!(
+            state.add(first_fragment(0, 32, &[1; 16]), 64),
+            Ok(None)
+        ));
+
+        match state.add(Packet::DataFragment(1, vec![2; 16]), 64) {
+            Ok(Some(Packet::Data(1, data))) => {
+                assert_eq!(data.len(), 32);
+                assert_eq!(&data[..16], &[1; 16]);
+                assert_eq!(&data[16..], &[2; 16]);
+            }
+            _ => panic!("Expected reassembled data packet"),
+        }
+    }
+
+    #[test]
+    fn test_too_many_fragments_discards_rest_of_message() {
+        let mut state = FragmentState::new(MAX_DATA_SIZE, MAX_FRAGMENTS_PER_BUFFER);
+        assert!(matches!(
+            state.add(first_fragment(0, 32, &[1; 8]), 64),
+            Ok(None)
+        ));
+        assert!(matches!(
+            state.add(Packet::DataFragment(1, vec![1; 8]), 64),
+            Ok(None)
+        ));
+        assert!(matches!(
+            state.add(Packet::DataFragment(2, vec![1; 8]), 64),
+            Err(DataError::TooManyFragments(2))
+        ));
+
+        // The last fragment of the rejected message must not be read as a new message length
+        assert!(matches!(
+            state.add(Packet::DataFragment(3, vec![0, 0, 0, 4, 1, 1, 1, 1]), 64),
+            Ok(None)
+        ));
+
+        assert!(matches!(
+            state.add(first_fragment(4, 4, &[3; 4]), 64),
+            Ok(Some(Packet::Data(4, _)))
+        ));
+    }
+
+    #[test]
+    fn test_data_too_large_discards_rest_of_message() {
+        let mut state = FragmentState::new(MAX_DATA_SIZE, MAX_FRAGMENTS_PER_BUFFER);
+        assert!(matches!(
+            state.add(first_fragment(0, MAX_DATA_SIZE + 1, &[1; 8]), 512),
+            Err(DataError::DataTooLarge(_))
+        ));
+        assert!(matches!(
+            state.add(Packet::DataFragment(1, vec![0, 0, 0, 4, 1, 1, 1, 1]), 512),
+            Ok(None)
+        ));
+    }
+}